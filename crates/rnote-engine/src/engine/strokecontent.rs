@@ -5,7 +5,9 @@ use crate::strokes::Stroke;
 use crate::Drawable;
 use p2d::bounding_volume::{Aabb, BoundingVolume};
 use rnote_compose::shapes::Shapeable;
+use rnote_compose::Color;
 use serde::{Deserialize, Serialize};
+use std::ops::Range;
 use std::sync::Arc;
 use tracing::warn;
 
@@ -24,6 +26,314 @@ pub struct StrokeContent {
     /// The optional background associated with the content.
     #[serde(rename = "background")]
     pub background: Option<Background>,
+    /// Optional per-group compositing layers. When non-empty, strokes are drawn grouped by
+    /// [`StrokeContentLayer::stroke_range`] instead of individually: each group is painted into
+    /// an isolated Cairo group surface, then composited back with its `blend_mode` and `opacity`.
+    /// When empty, every stroke is painted directly with the default `Over` operator, as before.
+    ///
+    /// A stroke not covered by any layer's `stroke_range` is not drawn at all once `layers` is
+    /// non-empty - layers are expected to partition `strokes`, not just annotate a subset of it.
+    /// A layer whose `stroke_range` is out of bounds for `strokes` (possible with hand-edited or
+    /// otherwise malformed `layers`) is skipped with a warning rather than panicking.
+    #[serde(rename = "layers")]
+    pub layers: Vec<StrokeContentLayer>,
+}
+
+/// A subset of CSS/SVG `mix-blend-mode` values supported when compositing a
+/// [`StrokeContentLayer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BlendMode {
+    /// Paint normally, with Cairo's default `Over` operator.
+    #[default]
+    Normal,
+    /// Multiply the layer's colors with what is already painted.
+    Multiply,
+    /// Screen the layer's colors against what is already painted.
+    Screen,
+    /// Overlay: a combination of `Multiply` and `Screen`.
+    Overlay,
+    /// Keep the darker of the layer's and the existing colors, per channel.
+    Darken,
+    /// Keep the lighter of the layer's and the existing colors, per channel.
+    Lighten,
+}
+
+impl BlendMode {
+    /// Returns the Cairo compositing operator corresponding to this blend mode.
+    pub fn as_cairo_operator(self) -> cairo::Operator {
+        match self {
+            Self::Normal => cairo::Operator::Over,
+            Self::Multiply => cairo::Operator::Multiply,
+            Self::Screen => cairo::Operator::Screen,
+            Self::Overlay => cairo::Operator::Overlay,
+            Self::Darken => cairo::Operator::Darken,
+            Self::Lighten => cairo::Operator::Lighten,
+        }
+    }
+}
+
+/// A group of strokes within a [`StrokeContent`] that is composited as a single unit.
+///
+/// Strokes are addressed by range into [`StrokeContent::strokes`], drawn into an isolated
+/// group surface, then painted back with `blend_mode` and `opacity`. This mirrors how a CSS/SVG
+/// stacking context with `mix-blend-mode` is established, and is what makes effects like
+/// highlighter-over-ink representable, since every stroke would otherwise be painted directly
+/// with the default `Over` operator.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename = "stroke_content_layer")]
+pub struct StrokeContentLayer {
+    /// The range into the parent [`StrokeContent::strokes`] that belongs to this layer.
+    #[serde(rename = "stroke_range")]
+    pub stroke_range: Range<usize>,
+    /// The blend mode the layer is composited with.
+    #[serde(rename = "blend_mode")]
+    pub blend_mode: BlendMode,
+    /// The opacity the whole layer is painted with, in the range `0.0..=1.0`.
+    #[serde(rename = "opacity")]
+    pub opacity: f64,
+}
+
+impl Default for StrokeContentLayer {
+    fn default() -> Self {
+        Self {
+            stroke_range: 0..0,
+            blend_mode: BlendMode::default(),
+            opacity: 1.0,
+        }
+    }
+}
+
+/// The small set of drawing primitives [`StrokeContent::draw_to_backend`] needs to render its
+/// clipping, scaling, and layer-compositing logic without being hard-wired to `cairo::Context`.
+///
+/// Implemented once for [`cairo::Context`]. The trait has no path, image or source-color
+/// primitives: [`Stroke`]/[`Background`] rendering goes through [`Self::as_cairo_context`], so
+/// every implementor must still provide a `cairo::Context` to draw strokes and backgrounds into.
+/// Only the clipping, scaling and group-compositing steps are routed through the trait.
+pub trait DrawingBackend {
+    /// Saves the current drawing state.
+    fn save(&self) -> anyhow::Result<()>;
+    /// Restores the previously saved drawing state.
+    fn restore(&self) -> anyhow::Result<()>;
+    /// Scales the coordinate system by the given per-axis factors.
+    fn scale(&self, sx: f64, sy: f64);
+    /// Intersects the current clip with the given rectangle.
+    fn clip_rect(&self, bounds: Aabb);
+    /// Starts redirecting drawing to an isolated group, to be finished with
+    /// [`Self::pop_group_composited`].
+    fn push_group(&self);
+    /// Finishes a group started with [`Self::push_group`], compositing it onto the target with
+    /// the given blend mode and opacity.
+    fn pop_group_composited(&self, blend_mode: BlendMode, opacity: f64) -> anyhow::Result<()>;
+    /// Returns the underlying Cairo context, used for all stroke and background drawing.
+    fn as_cairo_context(&self) -> &cairo::Context;
+}
+
+impl DrawingBackend for cairo::Context {
+    fn save(&self) -> anyhow::Result<()> {
+        cairo::Context::save(self)?;
+        Ok(())
+    }
+
+    fn restore(&self) -> anyhow::Result<()> {
+        cairo::Context::restore(self)?;
+        Ok(())
+    }
+
+    fn scale(&self, sx: f64, sy: f64) {
+        cairo::Context::scale(self, sx, sy);
+    }
+
+    fn clip_rect(&self, bounds: Aabb) {
+        self.rectangle(
+            bounds.mins[0],
+            bounds.mins[1],
+            bounds.extents()[0],
+            bounds.extents()[1],
+        );
+        self.clip();
+    }
+
+    fn push_group(&self) {
+        cairo::Context::push_group(self);
+    }
+
+    fn pop_group_composited(&self, blend_mode: BlendMode, opacity: f64) -> anyhow::Result<()> {
+        self.pop_group_to_source()?;
+        self.set_operator(blend_mode.as_cairo_operator());
+        self.paint_with_alpha(opacity)?;
+        self.set_operator(cairo::Operator::Over);
+        Ok(())
+    }
+
+    fn as_cairo_context(&self) -> &cairo::Context {
+        self
+    }
+}
+
+/// The target format for vector export via [`StrokeContent::generate_vector`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VectorFormat {
+    /// Portable Document Format.
+    Pdf,
+    /// PostScript.
+    Ps,
+    /// Encapsulated PostScript: a single page restricted to the content bounding box.
+    Eps,
+}
+
+/// Options controlling vector export via [`StrokeContent::generate_vector`].
+#[derive(Debug, Clone)]
+pub struct VectorExportOptions {
+    /// The vector format to export to.
+    pub format: VectorFormat,
+    /// The PDF version to restrict the output to. Only used for [`VectorFormat::Pdf`].
+    /// `None` lets Cairo pick its default (the newest version it supports).
+    pub pdf_version: Option<cairo::PdfVersion>,
+    /// The PostScript language level to restrict the output to. Only used for
+    /// [`VectorFormat::Ps`]/[`VectorFormat::Eps`]. `None` lets Cairo pick its default.
+    pub ps_level: Option<cairo::PsLevel>,
+    /// Whether to draw the background.
+    pub draw_background: bool,
+    /// Whether to draw the background pattern (if applicable).
+    pub draw_pattern: bool,
+    /// The color remapping strategy to apply to strokes before drawing them. The output is a
+    /// vector surface, so only [`ColorTransform::DarkestInk`] changes stroke colors here.
+    pub color_transform: ColorTransform,
+    /// The margin to add around the content.
+    pub margin: f64,
+    /// Restrict the generated surface to exactly the content bounding box, ignoring `margin`.
+    pub tight_bbox: bool,
+}
+
+impl Default for VectorExportOptions {
+    fn default() -> Self {
+        Self {
+            format: VectorFormat::Pdf,
+            pdf_version: None,
+            ps_level: None,
+            draw_background: true,
+            draw_pattern: true,
+            color_transform: ColorTransform::default(),
+            margin: StrokeContent::CLIPBOARD_EXPORT_MARGIN,
+            tight_bbox: false,
+        }
+    }
+}
+
+/// Target output sizing for [`StrokeContent::generate_svg`], modeled on `rsvg-convert`'s
+/// `--width`/`--height`/`--zoom`/`--dpi-x`/`--dpi-y` options.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputSize {
+    /// Scale the content uniformly by the given factor.
+    Zoom(f64),
+    /// Scale the content's x and y axes independently.
+    ZoomAxes {
+        /// The horizontal scale factor.
+        x: f64,
+        /// The vertical scale factor.
+        y: f64,
+    },
+    /// Resize to the given pixel dimensions. If only one of `width`/`height` is `Some`,
+    /// the other is derived from the content's aspect ratio.
+    Pixels {
+        /// The target width, in pixels.
+        width: Option<f64>,
+        /// The target height, in pixels.
+        height: Option<f64>,
+    },
+    /// Rescale from a source DPI to a target DPI, e.g. `96.0` to `300.0` for print.
+    Dpi {
+        /// The DPI the content is currently authored at.
+        source: f64,
+        /// The DPI to scale the output to.
+        target: f64,
+    },
+}
+
+impl Default for OutputSize {
+    fn default() -> Self {
+        Self::Zoom(1.0)
+    }
+}
+
+impl OutputSize {
+    /// Resolves this `OutputSize` against the content's intrinsic extents, returning the
+    /// per-axis scale factor to apply to the drawing.
+    pub fn resolve(&self, content_extents: na::Vector2<f64>) -> na::Vector2<f64> {
+        match *self {
+            Self::Zoom(zoom) => na::Vector2::new(zoom, zoom),
+            Self::ZoomAxes { x, y } => na::Vector2::new(x, y),
+            Self::Pixels { width, height } => match (width, height) {
+                (Some(width), Some(height)) => na::Vector2::new(
+                    width / content_extents[0],
+                    height / content_extents[1],
+                ),
+                (Some(width), None) => {
+                    let scale = width / content_extents[0];
+                    na::Vector2::new(scale, scale)
+                }
+                (None, Some(height)) => {
+                    let scale = height / content_extents[1];
+                    na::Vector2::new(scale, scale)
+                }
+                (None, None) => na::Vector2::new(1.0, 1.0),
+            },
+            Self::Dpi { source, target } => {
+                let scale = target / source;
+                na::Vector2::new(scale, scale)
+            }
+        }
+    }
+}
+
+/// A color remapping strategy applied to vector strokes before they are drawn, generalizing the
+/// previous `optimize_printing`-only "darkest ink" behavior.
+///
+/// As with the prior `optimize_printing` flag, strokes enclosed by a `BitmapImage`/`VectorImage`
+/// rectangle are left untouched, since rasterized content can't be sensibly remapped this way.
+///
+/// Only [`Self::DarkestInk`] is forwarded to the background/pattern; `Grayscale`, `Monochrome`
+/// and `Remap` apply to strokes only, so e.g. a "clean black-and-white handout" still needs
+/// `draw_background: false` if the background isn't already black/white/transparent.
+///
+/// `Grayscale`, `Monochrome` and `Remap` are applied only when drawing to a raster surface. On
+/// vector surfaces (PDF, PostScript, SVG), which includes the output of
+/// [`StrokeContent::generate_svg`] and [`StrokeContent::generate_vector`], strokes keep their
+/// original colors under these variants; only `DarkestInk` takes effect there.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum ColorTransform {
+    /// Draw strokes with their original colors.
+    #[default]
+    None,
+    /// Collapse each stroke to its single darkest color. The previous `optimize_printing`
+    /// behavior, kept as its own variant since it operates on a whole stroke rather than
+    /// remapping individual colors.
+    DarkestInk,
+    /// Convert stroke colors to luminance-weighted grayscale.
+    Grayscale,
+    /// Convert stroke colors to pure black/white based on a grayscale luminance threshold.
+    Monochrome {
+        /// The luminance threshold in `0.0..=1.0` below which a color becomes black.
+        threshold: f64,
+    },
+    /// Substitute colors using an explicit `(from, to)` palette, leaving unmatched colors as-is.
+    Remap(Vec<(Color, Color)>),
+}
+
+impl ColorTransform {
+    /// Applies this transform to a single color. Returns the color unchanged for
+    /// [`Self::None`] and [`Self::DarkestInk`], since the latter operates on a whole stroke
+    /// rather than a single color; see [`StrokeContent::draw_stroke_transformed`].
+    pub fn apply(&self, color: Color) -> Color {
+        match self {
+            Self::None | Self::DarkestInk => color,
+            Self::Grayscale => crate::utils::color_to_grayscale(color),
+            Self::Monochrome { threshold } => crate::utils::color_to_monochrome(color, *threshold),
+            Self::Remap(palette) => crate::utils::color_remap(color, palette),
+        }
+    }
 }
 
 impl StrokeContent {
@@ -50,6 +360,12 @@ impl StrokeContent {
         self
     }
 
+    /// Creates a new `StrokeContent` with the specified compositing layers.
+    pub fn with_layers(mut self, layers: Vec<StrokeContentLayer>) -> Self {
+        self.layers = layers;
+        self
+    }
+
     /// Calculates the bounding box of the `StrokeContent`.
     ///
     /// If `bounds` is `Some`, it is returned directly. Otherwise, the bounding box is calculated
@@ -75,6 +391,191 @@ impl StrokeContent {
         self.bounds().map(|b| b.extents())
     }
 
+    /// Calculates the bounds of the `StrokeContent` from the strokes' actual rendered ink,
+    /// rather than their nominal geometric [`Self::bounds`].
+    ///
+    /// Each stroke is replayed into a throwaway Cairo recording surface, and the surface's
+    /// `ink_extents` (accounting for stroke width, line caps/joins, and rendered image
+    /// antialiasing) are merged into the returned `Aabb`. This is more expensive than
+    /// [`Self::bounds`], but gives a tighter, artifact-free clip rectangle and margin for
+    /// export, since the geometric bounds can clip edges or leave uneven margins around the
+    /// actual painted pixels.
+    pub fn ink_bounds(&self) -> anyhow::Result<Option<Aabb>> {
+        if self.strokes.is_empty() {
+            return Ok(self.bounds());
+        }
+        let mut ink_bounds = Aabb::new_invalid();
+
+        for stroke in self.strokes.iter() {
+            let recording = cairo::RecordingSurface::create(cairo::Content::ColorAlpha, None)?;
+            let cairo_cx = cairo::Context::new(&recording)?;
+            stroke.draw_to_cairo(&cairo_cx, 1.0)?;
+            drop(cairo_cx);
+
+            let (x, y, width, height) = recording.ink_extents();
+            ink_bounds = ink_bounds.merged(&Aabb::new(
+                na::point![x, y],
+                na::point![x + width, y + height],
+            ));
+        }
+
+        Ok(Some(ink_bounds))
+    }
+
+    /// Generates a PDF representation of the `StrokeContent` and returns its bytes.
+    ///
+    /// Convenience wrapper around [`Self::generate_vector`] for [`VectorFormat::Pdf`].
+    pub fn generate_pdf(
+        &self,
+        draw_background: bool,
+        draw_pattern: bool,
+        color_transform: ColorTransform,
+        margin: f64,
+        pdf_version: Option<cairo::PdfVersion>,
+    ) -> anyhow::Result<Option<Vec<u8>>> {
+        self.generate_vector(VectorExportOptions {
+            format: VectorFormat::Pdf,
+            pdf_version,
+            draw_background,
+            draw_pattern,
+            color_transform,
+            margin,
+            ..Default::default()
+        })
+    }
+
+    /// Generates a multi-page-capable PostScript representation of the `StrokeContent` and
+    /// returns its bytes.
+    ///
+    /// Convenience wrapper around [`Self::generate_vector`] for [`VectorFormat::Ps`].
+    pub fn generate_ps(
+        &self,
+        draw_background: bool,
+        draw_pattern: bool,
+        color_transform: ColorTransform,
+        margin: f64,
+        ps_level: Option<cairo::PsLevel>,
+    ) -> anyhow::Result<Option<Vec<u8>>> {
+        self.generate_vector(VectorExportOptions {
+            format: VectorFormat::Ps,
+            ps_level,
+            draw_background,
+            draw_pattern,
+            color_transform,
+            margin,
+            ..Default::default()
+        })
+    }
+
+    /// Generates an Encapsulated PostScript (EPS) representation of the `StrokeContent`,
+    /// restricted to a single page sized to its bounding box, and returns its bytes.
+    ///
+    /// Convenience wrapper around [`Self::generate_vector`] for [`VectorFormat::Eps`].
+    pub fn generate_eps(
+        &self,
+        draw_background: bool,
+        draw_pattern: bool,
+        color_transform: ColorTransform,
+        margin: f64,
+        ps_level: Option<cairo::PsLevel>,
+    ) -> anyhow::Result<Option<Vec<u8>>> {
+        self.generate_vector(VectorExportOptions {
+            format: VectorFormat::Eps,
+            ps_level,
+            draw_background,
+            draw_pattern,
+            color_transform,
+            margin,
+            ..Default::default()
+        })
+    }
+
+    /// Generates a vector (PDF/PostScript/EPS) representation of the `StrokeContent` and
+    /// returns its bytes.
+    ///
+    /// Sizes the target surface to `self.bounds()` loosened by `options.margin` (or to the
+    /// exact content bounding box when `options.tight_bbox` is set), then replays
+    /// [`Self::draw_to_cairo`] against a Cairo context on that surface, reusing all of its
+    /// clipping and background drawing logic. Returns `None` if the content has no bounds.
+    pub fn generate_vector(
+        &self,
+        options: VectorExportOptions,
+    ) -> anyhow::Result<Option<Vec<u8>>> {
+        let Some(bounds) = self.bounds() else {
+            return Ok(None);
+        };
+        let bounds = if options.tight_bbox {
+            bounds
+        } else {
+            bounds.loosened(options.margin)
+        };
+        let margin = if options.tight_bbox { 0.0 } else { options.margin };
+        let extents = bounds.extents();
+
+        let draw = |cairo_cx: &cairo::Context| -> anyhow::Result<()> {
+            cairo_cx.translate(-bounds.mins[0], -bounds.mins[1]);
+            self.draw_to_cairo(
+                cairo_cx,
+                options.draw_background,
+                options.draw_pattern,
+                options.color_transform.clone(),
+                margin,
+                1.0,
+                na::Vector2::new(1.0, 1.0),
+                None,
+            )
+        };
+
+        let bytes = match options.format {
+            VectorFormat::Pdf => {
+                let surface = cairo::PdfSurface::for_stream(extents[0], extents[1], Vec::new())?;
+                if let Some(version) = options.pdf_version {
+                    surface.restrict_to_version(version)?;
+                }
+                let cairo_cx = cairo::Context::new(&surface)?;
+                draw(&cairo_cx)?;
+                drop(cairo_cx);
+                surface.finish();
+                *surface
+                    .finish_output_stream()
+                    .map_err(|e| anyhow::anyhow!("failed to finish PDF output stream, Err: {e:?}"))?
+                    .downcast::<Vec<u8>>()
+                    .map_err(|_| anyhow::anyhow!("failed to downcast PDF output stream"))?
+            }
+            VectorFormat::Ps | VectorFormat::Eps => {
+                let surface = cairo::PsSurface::for_stream(extents[0], extents[1], Vec::new())?;
+                surface.set_eps(options.format == VectorFormat::Eps);
+                if let Some(level) = options.ps_level {
+                    surface.restrict_to_level(level)?;
+                }
+                let cairo_cx = cairo::Context::new(&surface)?;
+                draw(&cairo_cx)?;
+                drop(cairo_cx);
+                surface.finish();
+                *surface
+                    .finish_output_stream()
+                    .map_err(|e| anyhow::anyhow!("failed to finish PS output stream, Err: {e:?}"))?
+                    .downcast::<Vec<u8>>()
+                    .map_err(|_| anyhow::anyhow!("failed to downcast PS output stream"))?
+            }
+        };
+
+        Ok(Some(bytes))
+    }
+
+    /// Scales both the extents and the origin of `bounds` by `scale`, i.e. maps every point `p`
+    /// in `bounds` to `p.component_mul(&scale)`.
+    ///
+    /// This is *not* the same as scaling the extents while keeping `mins` fixed: the matrix
+    /// [`Self::draw_to_backend`] sets up scales around the origin, so a content point `p` ends up
+    /// at `scale * p`, not `scale * (p - bounds.mins) + bounds.mins`. The target bounds passed to
+    /// [`Svg::gen_with_cairo`] need the former to line up with what actually gets drawn.
+    fn scale_aabb(bounds: Aabb, scale: na::Vector2<f64>) -> Aabb {
+        let scaled_mins = na::Point2::from(bounds.mins.coords.component_mul(&scale));
+        let scaled_extents = bounds.extents().component_mul(&scale);
+        Aabb::new(scaled_mins, scaled_mins + scaled_extents)
+    }
+
     /// Generates an SVG representation of the `StrokeContent`.
     ///
     /// The generated SVG will have its bounds moved to the origin (0, 0).
@@ -83,34 +584,55 @@ impl StrokeContent {
     ///
     /// * `draw_background` - Whether to draw the background in the SVG.
     /// * `draw_pattern` - Whether to draw the background pattern (if applicable).
-    /// * `optimize_printing` - Whether to apply optimizations for printing.
+    /// * `color_transform` - The color remapping strategy to apply to strokes before drawing
+    ///                         them. The SVG is a vector surface, so only
+    ///                         [`ColorTransform::DarkestInk`] changes stroke colors here.
     /// * `margin` - The margin to add around the content.
+    /// * `output_size` - The target output sizing (zoom, pixel dimensions, or DPI scaling) to
+    ///                     apply to the generated SVG.
+    /// * `use_ink_bounds` - Whether to compute the clip rectangle and loosened bounds from
+    ///                       [`Self::ink_bounds`] instead of [`Self::bounds`].
     ///
     /// # Returns
     ///
     /// An `Svg` object representing the content, or `None` if the content has no bounds.
+    #[allow(clippy::too_many_arguments)]
     pub fn generate_svg(
         &self,
         draw_background: bool,
         draw_pattern: bool,
-        optimize_printing: bool,
+        color_transform: ColorTransform,
         margin: f64,
+        output_size: OutputSize,
+        use_ink_bounds: bool,
     ) -> anyhow::Result<Option<Svg>> {
-        let Some(bounds_loosened) = self.bounds().map(|b| b.loosened(margin)) else {
+        let raw_bounds = if use_ink_bounds {
+            self.ink_bounds()?
+        } else {
+            self.bounds()
+        };
+        let Some(bounds_loosened) = raw_bounds.map(|b| b.loosened(margin)) else {
             return Ok(None);
         };
+        let output_scale = output_size.resolve(bounds_loosened.extents());
+        let scaled_bounds = Self::scale_aabb(bounds_loosened, output_scale);
         let mut svg = Svg::gen_with_cairo(
             |cairo_cx| {
                 self.draw_to_cairo(
                     cairo_cx,
                     draw_background,
                     draw_pattern,
-                    optimize_printing,
+                    color_transform.clone(),
                     margin,
-                    1.0,
+                    output_scale.amax(),
+                    output_scale,
+                    // Pass the bounds computed above so the clip matches them: without an
+                    // override `draw_to_backend` falls back to the geometric `Self::bounds`,
+                    // even when `use_ink_bounds` is set.
+                    raw_bounds,
                 )
             },
-            bounds_loosened,
+            scaled_bounds,
         )?;
         // The simplification also moves the bounds to mins: [0.0, 0.0], maxs: extents
         if let Err(e) = svg.simplify() {
@@ -126,54 +648,91 @@ impl StrokeContent {
     /// * `cairo_cx` - The Cairo context to draw to.
     /// * `draw_background` - Whether to draw the background.
     /// * `draw_pattern` - Whether to draw the background pattern (if applicable).
-    /// * `optimize_printing` - Whether to apply optimizations for printing.
-    ///                           When true it draws only the darkest color of a vector stroke,
-    ///                           if the stroke is not inside of an image.
+    /// * `color_transform` - The color remapping strategy to apply to strokes before drawing
+    ///                         them, generalizing the previous `optimize_printing` flag.
+    ///                         Strokes enclosed by an image are left untouched, as before.
     /// * `margin` - The margin to add around the content when drawing.
     /// * `image_scale` - The scaling factor for images.
+    /// * `output_scale` - The per-axis output scale factor (see [`OutputSize::resolve`]),
+    ///                      applied to the Cairo matrix before any drawing happens.
+    /// * `bounds_override` - Bounds to use instead of [`Self::bounds`], e.g. a pre-computed
+    ///                         [`Self::ink_bounds`] result. Passing the already-computed value
+    ///                         avoids recomputing the (expensive) ink bounds on every call.
+    ///
+    /// Thin wrapper around [`Self::draw_to_backend`] for the [`cairo::Context`] backend.
+    #[allow(clippy::too_many_arguments)]
     pub fn draw_to_cairo(
         &self,
         cairo_cx: &cairo::Context,
         draw_background: bool,
         draw_pattern: bool,
-        optimize_printing: bool,
+        color_transform: ColorTransform,
         margin: f64,
         image_scale: f64,
+        output_scale: na::Vector2<f64>,
+        bounds_override: Option<Aabb>,
     ) -> anyhow::Result<()> {
-        let Some(bounds) = self.bounds() else {
+        self.draw_to_backend(
+            cairo_cx,
+            draw_background,
+            draw_pattern,
+            color_transform,
+            margin,
+            image_scale,
+            output_scale,
+            bounds_override,
+        )
+    }
+
+    /// Draws the `StrokeContent` through a [`DrawingBackend`], generic over the rendering target.
+    ///
+    /// Arguments are identical to [`Self::draw_to_cairo`], which delegates to this method.
+    /// Stroke and background drawing go through [`DrawingBackend::as_cairo_context`], since
+    /// [`Stroke`]/[`Background`] draw only to a `cairo::Context`; the clipping, scaling and
+    /// layer-compositing logic is generic over `B`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_to_backend<B: DrawingBackend>(
+        &self,
+        backend: &B,
+        draw_background: bool,
+        draw_pattern: bool,
+        color_transform: ColorTransform,
+        margin: f64,
+        image_scale: f64,
+        output_scale: na::Vector2<f64>,
+        bounds_override: Option<Aabb>,
+    ) -> anyhow::Result<()> {
+        let Some(bounds) = bounds_override.or_else(|| self.bounds()) else {
             return Ok(());
         };
         let bounds_loosened = bounds.loosened(margin);
 
-        cairo_cx.save()?;
-        cairo_cx.rectangle(
-            bounds_loosened.mins[0],
-            bounds_loosened.mins[1],
-            bounds_loosened.extents()[0],
-            bounds_loosened.extents()[1],
-        );
-        cairo_cx.clip();
+        // Outer save/restore so the scale set up for drawing doesn't leak into the caller's
+        // context once this returns - `draw_to_cairo` is public and may be called against a
+        // long-lived `cairo::Context` the caller keeps using afterward.
+        backend.save()?;
+        backend.scale(output_scale[0], output_scale[1]);
+        backend.save()?;
+        backend.clip_rect(bounds_loosened);
 
         if draw_background {
             if let Some(background) = &self.background {
+                // Background drawing has not been generalized to `ColorTransform`; it keeps
+                // its own `optimize_printing`-style darkest-ink behavior only, so `Grayscale`,
+                // `Monochrome` and `Remap` leave it in full color - see the limitation documented
+                // on `ColorTransform` itself.
                 background.draw_to_cairo(
-                    cairo_cx,
+                    backend.as_cairo_context(),
                     bounds_loosened,
                     draw_pattern,
-                    optimize_printing,
+                    color_transform == ColorTransform::DarkestInk,
                 )?;
             }
         }
 
-        cairo_cx.restore()?;
-        cairo_cx.save()?;
-        cairo_cx.rectangle(
-            bounds.mins[0],
-            bounds.mins[1],
-            bounds.extents()[0],
-            bounds.extents()[1],
-        );
-        cairo_cx.clip();
+        backend.restore()?;
+        backend.save()?;
+        backend.clip_rect(bounds);
 
         let image_bounds = self
             .strokes
@@ -185,28 +744,272 @@ impl StrokeContent {
             })
             .collect::<Vec<Aabb>>();
 
-        for stroke in self.strokes.iter() {
-            let stroke_bounds = stroke.bounds();
-
-            if optimize_printing
-                && image_bounds
-                    .iter()
-                    .all(|bounds| !bounds.contains(&stroke_bounds))
-            {
-                // Using the stroke's bounds instead of hitboxes works for inclusion.
-                // If this is changed to intersection, all hitboxes must be checked individually.
-
-                let mut darkest_color_stroke = stroke.as_ref().clone();
-                darkest_color_stroke.set_to_darkest_color();
-
-                darkest_color_stroke.draw_to_cairo(cairo_cx, image_scale)?;
-            } else {
-                stroke.draw_to_cairo(cairo_cx, image_scale)?;
+        if self.layers.is_empty() {
+            for stroke in self.strokes.iter() {
+                Self::draw_stroke_transformed(
+                    stroke,
+                    backend.as_cairo_context(),
+                    &image_bounds,
+                    &color_transform,
+                    image_scale,
+                )?;
+            }
+        } else {
+            for layer in self.layers.iter() {
+                let Some(layer_strokes) = self.strokes.get(layer.stroke_range.clone()) else {
+                    warn!(
+                        "Skipping StrokeContentLayer with out-of-bounds stroke_range {:?}, strokes.len() = {}",
+                        layer.stroke_range,
+                        self.strokes.len()
+                    );
+                    continue;
+                };
+                backend.push_group();
+
+                for stroke in layer_strokes.iter() {
+                    Self::draw_stroke_transformed(
+                        stroke,
+                        backend.as_cairo_context(),
+                        &image_bounds,
+                        &color_transform,
+                        image_scale,
+                    )?;
+                }
+
+                backend.pop_group_composited(layer.blend_mode, layer.opacity)?;
+            }
+        }
+
+        backend.restore()?;
+        backend.restore()?;
+
+        Ok(())
+    }
+
+    /// Returns whether `cairo_cx`'s target surface records vector drawing commands (PDF,
+    /// PostScript, SVG) rather than rasterizing directly, i.e. whether baking a raster image
+    /// into it would defeat the purpose of a vector export.
+    fn is_vector_surface(cairo_cx: &cairo::Context) -> bool {
+        matches!(
+            cairo_cx.target().type_(),
+            cairo::SurfaceType::Pdf | cairo::SurfaceType::Ps | cairo::SurfaceType::Svg
+        )
+    }
+
+    /// Draws a single stroke to `cairo_cx`, applying `color_transform` unless it is
+    /// [`ColorTransform::None`] or the stroke is enclosed by one of `image_bounds`. On a vector
+    /// surface only [`ColorTransform::DarkestInk`] is applied.
+    fn draw_stroke_transformed(
+        stroke: &Arc<Stroke>,
+        cairo_cx: &cairo::Context,
+        image_bounds: &[Aabb],
+        color_transform: &ColorTransform,
+        image_scale: f64,
+    ) -> anyhow::Result<()> {
+        let stroke_bounds = stroke.bounds();
+
+        if *color_transform != ColorTransform::None
+            && image_bounds
+                .iter()
+                .all(|bounds| !bounds.contains(&stroke_bounds))
+        {
+            // Using the stroke's bounds instead of hitboxes works for inclusion.
+            // If this is changed to intersection, all hitboxes must be checked individually.
+
+            match color_transform {
+                ColorTransform::DarkestInk => {
+                    let mut darkest_color_stroke = stroke.as_ref().clone();
+                    darkest_color_stroke.set_to_darkest_color();
+                    darkest_color_stroke.draw_to_cairo(cairo_cx, image_scale)
+                }
+                // `Grayscale`/`Monochrome`/`Remap` have no vector-native implementation (see
+                // `draw_stroke_pixel_transformed`'s doc comment) and can only be applied by
+                // rasterizing the stroke. Doing that against a vector surface would silently
+                // embed a bitmap in the PDF/PS/EPS/SVG output, defeating the point of a vector
+                // export, so such surfaces keep the stroke's original colors instead.
+                other if Self::is_vector_surface(cairo_cx) => {
+                    stroke.draw_to_cairo(cairo_cx, image_scale)
+                }
+                other => {
+                    Self::draw_stroke_pixel_transformed(stroke, cairo_cx, other, image_scale)
+                }
+            }
+        } else {
+            stroke.draw_to_cairo(cairo_cx, image_scale)
+        }
+    }
+
+    /// Draws a single stroke with `color_transform` applied per pixel.
+    ///
+    /// `Stroke` has no generic per-variant color mapper (only the narrower
+    /// `set_to_darkest_color` used by [`ColorTransform::DarkestInk`]), so `Grayscale`,
+    /// `Monochrome` and `Remap` are applied by rasterizing the stroke into a throwaway
+    /// `cairo::ImageSurface`, remapping its un-premultiplied pixel colors, and painting the
+    /// result back in place of the original vector drawing. Only reached for raster surfaces;
+    /// [`Self::draw_stroke_transformed`] keeps vector surfaces on the original vector colors.
+    fn draw_stroke_pixel_transformed(
+        stroke: &Arc<Stroke>,
+        cairo_cx: &cairo::Context,
+        color_transform: &ColorTransform,
+        image_scale: f64,
+    ) -> anyhow::Result<()> {
+        let bounds = stroke.bounds();
+        let extents = bounds.extents();
+        let width = ((extents[0] * image_scale).ceil() as i32).max(1);
+        let height = ((extents[1] * image_scale).ceil() as i32).max(1);
+
+        let raster = cairo::ImageSurface::create(cairo::Format::ARgb32, width, height)?;
+        {
+            let raster_cx = cairo::Context::new(&raster)?;
+            raster_cx.scale(image_scale, image_scale);
+            raster_cx.translate(-bounds.mins[0], -bounds.mins[1]);
+            stroke.draw_to_cairo(&raster_cx, image_scale)?;
+        }
+        raster.flush();
+
+        {
+            let stride = raster.stride() as usize;
+            let mut raster_data = raster.data()?;
+
+            for row in raster_data.chunks_mut(stride) {
+                for pixel in row[..(width as usize) * 4].chunks_mut(4) {
+                    // Cairo's ARgb32 is premultiplied alpha, native-endian BGRA on little-endian.
+                    let alpha = pixel[3] as f64 / 255.0;
+                    if alpha <= 0.0 {
+                        continue;
+                    }
+                    let straight_color = Color {
+                        r: (pixel[2] as f64 / 255.0) / alpha,
+                        g: (pixel[1] as f64 / 255.0) / alpha,
+                        b: (pixel[0] as f64 / 255.0) / alpha,
+                        a: alpha,
+                    };
+                    let transformed = color_transform.apply(straight_color);
+
+                    pixel[0] = ((transformed.b * transformed.a).clamp(0.0, 1.0) * 255.0) as u8;
+                    pixel[1] = ((transformed.g * transformed.a).clamp(0.0, 1.0) * 255.0) as u8;
+                    pixel[2] = ((transformed.r * transformed.a).clamp(0.0, 1.0) * 255.0) as u8;
+                    pixel[3] = (transformed.a.clamp(0.0, 1.0) * 255.0) as u8;
+                }
             }
         }
+        raster.mark_dirty();
 
+        cairo_cx.save()?;
+        cairo_cx.translate(bounds.mins[0], bounds.mins[1]);
+        cairo_cx.scale(1.0 / image_scale, 1.0 / image_scale);
+        cairo_cx.set_source_surface(&raster, 0.0, 0.0)?;
+        cairo_cx.paint()?;
         cairo_cx.restore()?;
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    /// A [`DrawingBackend`] that performs no real drawing, recording how many times
+    /// [`DrawingBackend::push_group`]/[`DrawingBackend::pop_group_composited`] were called so
+    /// tests can assert a layer was (or wasn't) actually drawn.
+    struct RecordingBackend {
+        cairo_cx: cairo::Context,
+        push_group_calls: RefCell<usize>,
+        pop_group_calls: RefCell<usize>,
+    }
+
+    impl RecordingBackend {
+        fn new() -> Self {
+            let surface = cairo::ImageSurface::create(cairo::Format::ARgb32, 1, 1).unwrap();
+            Self {
+                cairo_cx: cairo::Context::new(&surface).unwrap(),
+                push_group_calls: RefCell::new(0),
+                pop_group_calls: RefCell::new(0),
+            }
+        }
+    }
+
+    impl DrawingBackend for RecordingBackend {
+        fn save(&self) -> anyhow::Result<()> {
+            Ok(())
+        }
+        fn restore(&self) -> anyhow::Result<()> {
+            Ok(())
+        }
+        fn scale(&self, _sx: f64, _sy: f64) {}
+        fn clip_rect(&self, _bounds: Aabb) {}
+        fn push_group(&self) {
+            *self.push_group_calls.borrow_mut() += 1;
+        }
+        fn pop_group_composited(&self, _blend_mode: BlendMode, _opacity: f64) -> anyhow::Result<()> {
+            *self.pop_group_calls.borrow_mut() += 1;
+            Ok(())
+        }
+        fn as_cairo_context(&self) -> &cairo::Context {
+            &self.cairo_cx
+        }
+    }
+
+    #[test]
+    fn draw_to_backend_skips_out_of_bounds_layer_range_without_panicking() {
+        let content = StrokeContent {
+            strokes: vec![],
+            bounds: Some(Aabb::new(na::Point2::new(0.0, 0.0), na::Point2::new(10.0, 10.0))),
+            background: None,
+            layers: vec![StrokeContentLayer {
+                // Out of bounds for the empty `strokes` above - this is what a hand-edited or
+                // otherwise malformed deserialized `StrokeContent` could produce.
+                stroke_range: 0..5,
+                ..Default::default()
+            }],
+        };
+        let backend = RecordingBackend::new();
+
+        let result = content.draw_to_backend(
+            &backend,
+            false,
+            false,
+            ColorTransform::None,
+            0.0,
+            1.0,
+            na::Vector2::new(1.0, 1.0),
+            None,
+        );
+
+        assert!(result.is_ok());
+        // The malformed layer must be skipped, not drawn with a truncated/panicking range.
+        assert_eq!(*backend.push_group_calls.borrow(), 0);
+        assert_eq!(*backend.pop_group_calls.borrow(), 0);
+    }
+
+    #[test]
+    fn scale_aabb_scales_origin_with_extents() {
+        let bounds = Aabb::new(na::Point2::new(10.0, 20.0), na::Point2::new(30.0, 50.0));
+        let scaled = StrokeContent::scale_aabb(bounds, na::Vector2::new(2.0, 3.0));
+
+        // mins scale the same way as the content, not just the extents - otherwise non-origin
+        // content is offset relative to what `draw_to_backend`'s scaling matrix actually draws.
+        assert_eq!(scaled.mins, na::Point2::new(20.0, 60.0));
+        assert_eq!(scaled.maxs, na::Point2::new(60.0, 150.0));
+    }
+
+    #[test]
+    fn scale_aabb_is_identity_for_unit_scale() {
+        let bounds = Aabb::new(na::Point2::new(-5.0, 2.5), na::Point2::new(7.0, 9.0));
+        let scaled = StrokeContent::scale_aabb(bounds, na::Vector2::new(1.0, 1.0));
+
+        assert_eq!(scaled.mins, bounds.mins);
+        assert_eq!(scaled.maxs, bounds.maxs);
+    }
+
+    #[test]
+    fn scale_aabb_scales_origin_aligned_bounds_unchanged_in_origin() {
+        let bounds = Aabb::new(na::Point2::new(0.0, 0.0), na::Point2::new(4.0, 8.0));
+        let scaled = StrokeContent::scale_aabb(bounds, na::Vector2::new(2.0, 0.5));
+
+        assert_eq!(scaled.mins, na::Point2::new(0.0, 0.0));
+        assert_eq!(scaled.maxs, na::Point2::new(8.0, 4.0));
+    }
+}
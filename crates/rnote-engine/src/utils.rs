@@ -107,6 +107,58 @@ pub fn p2d_aabb_to_geo_polygon(aabb: Aabb) -> geo::Polygon<f64> {
     geo::Polygon::new(line_string, vec![])
 }
 
+/// Converts a [`Color`] to its luminance-weighted grayscale equivalent, keeping alpha unchanged.
+///
+/// Uses the Rec. 601 luma weights, applied per channel in the `0.0..=1.0` color space.
+pub fn color_to_grayscale(color: Color) -> Color {
+    let luma = 0.299 * color.r + 0.587 * color.g + 0.114 * color.b;
+    Color {
+        r: luma,
+        g: luma,
+        b: luma,
+        a: color.a,
+    }
+}
+
+/// Converts a [`Color`] to pure black or white depending on whether its grayscale luminance is
+/// below `threshold` (`0.0..=1.0`), keeping alpha unchanged.
+pub fn color_to_monochrome(color: Color, threshold: f64) -> Color {
+    let luma = color_to_grayscale(color).r;
+    let value = if luma < threshold { 0.0 } else { 1.0 };
+    Color {
+        r: value,
+        g: value,
+        b: value,
+        a: color.a,
+    }
+}
+
+/// RGB channels within this tolerance of each other are considered equal by [`color_remap`].
+///
+/// The raster color transform path (via `ColorTransform::apply`) reconstructs straight-alpha
+/// colors from 8-bit premultiplied pixels, which only approximates the original channel values,
+/// so exact equality would rarely match. `1.0 / 255.0` is one unit of 8-bit quantization; a
+/// premultiplied channel is off by at most half a unit, and un-premultiplying divides that error
+/// by alpha. This tolerance therefore covers pixels with alpha down to about `0.25`; fainter
+/// pixels, such as antialiased edges, may fall outside it and keep their color.
+const COLOR_REMAP_EPSILON: f64 = 2.0 / 255.0;
+
+/// Remaps `color` to its paired replacement in `palette`, if present, leaving it unchanged
+/// otherwise. RGB channels are compared within [`COLOR_REMAP_EPSILON`] rather than exact
+/// equality, since callers may be matching against a color reconstructed from quantized raster
+/// pixels rather than the canonical value; alpha is never compared.
+pub fn color_remap(color: Color, palette: &[(Color, Color)]) -> Color {
+    palette
+        .iter()
+        .find(|(from, _)| {
+            (from.r - color.r).abs() <= COLOR_REMAP_EPSILON
+                && (from.g - color.g).abs() <= COLOR_REMAP_EPSILON
+                && (from.b - color.b).abs() <= COLOR_REMAP_EPSILON
+        })
+        .map(|(_, to)| *to)
+        .unwrap_or(color)
+}
+
 /// Returns a range where the start is always less than or equal to the end.
 pub fn positive_range<I>(first: I, second: I) -> Range<I>
 where
@@ -119,6 +171,81 @@ where
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn color_to_grayscale_weighs_green_highest() {
+        let red = Color { r: 1.0, g: 0.0, b: 0.0, a: 1.0 };
+        let green = Color { r: 0.0, g: 1.0, b: 0.0, a: 1.0 };
+        let blue = Color { r: 0.0, g: 0.0, b: 1.0, a: 1.0 };
+
+        // Rec. 601 weighs green highest, then red, then blue.
+        assert!(color_to_grayscale(green).r > color_to_grayscale(red).r);
+        assert!(color_to_grayscale(red).r > color_to_grayscale(blue).r);
+    }
+
+    #[test]
+    fn color_to_grayscale_preserves_alpha() {
+        let color = Color { r: 0.2, g: 0.4, b: 0.6, a: 0.5 };
+        assert_eq!(color_to_grayscale(color).a, 0.5);
+    }
+
+    #[test]
+    fn color_to_monochrome_thresholds_to_black_or_white() {
+        let dark = Color { r: 0.1, g: 0.1, b: 0.1, a: 1.0 };
+        let light = Color { r: 0.9, g: 0.9, b: 0.9, a: 1.0 };
+
+        let black = color_to_monochrome(dark, 0.5);
+        let white = color_to_monochrome(light, 0.5);
+
+        assert_eq!((black.r, black.g, black.b), (0.0, 0.0, 0.0));
+        assert_eq!((white.r, white.g, white.b), (1.0, 1.0, 1.0));
+        assert_eq!(black.a, 1.0);
+    }
+
+    #[test]
+    fn color_remap_substitutes_exact_matches() {
+        let from = Color { r: 1.0, g: 0.0, b: 0.0, a: 1.0 };
+        let to = Color { r: 0.0, g: 0.0, b: 1.0, a: 1.0 };
+        let palette = [(from, to)];
+
+        let remapped = color_remap(from, &palette);
+        assert_eq!((remapped.r, remapped.g, remapped.b), (to.r, to.g, to.b));
+    }
+
+    #[test]
+    fn color_remap_tolerates_quantization_error_from_raster_reconstruction() {
+        let from = Color { r: 0.6, g: 0.2, b: 0.8, a: 1.0 };
+        let to = Color { r: 0.0, g: 0.0, b: 0.0, a: 1.0 };
+        let palette = [(from, to)];
+
+        // Simulates a color reconstructed from 8-bit premultiplied pixels: each channel is off
+        // by up to one unit of 8-bit quantization, as described on `COLOR_REMAP_EPSILON`.
+        let reconstructed = Color {
+            r: from.r + 1.0 / 255.0,
+            g: from.g - 1.0 / 255.0,
+            b: from.b + 1.0 / 255.0,
+            a: 1.0,
+        };
+
+        let remapped = color_remap(reconstructed, &palette);
+        assert_eq!((remapped.r, remapped.g, remapped.b), (to.r, to.g, to.b));
+    }
+
+    #[test]
+    fn color_remap_leaves_unmatched_colors_unchanged() {
+        let from = Color { r: 1.0, g: 0.0, b: 0.0, a: 1.0 };
+        let to = Color { r: 0.0, g: 0.0, b: 1.0, a: 1.0 };
+        let palette = [(from, to)];
+        let unrelated = Color { r: 0.0, g: 1.0, b: 0.0, a: 1.0 };
+
+        let remapped = color_remap(unrelated, &palette);
+        assert_eq!((remapped.r, remapped.g, remapped.b), (unrelated.r, unrelated.g, unrelated.b));
+    }
+}
+
 /// (De)serializes a [glib::Bytes] with base64 encoding.
 pub mod glib_bytes_base64 {
     use serde::{Deserializer, Serializer};